@@ -2,41 +2,488 @@
 //!
 //! On windows, it uses `powershell_script` crate and on unix, it use `run_script` crate.
 
+use std::collections::HashMap;
+
+/// Which shell is used to execute a script.
+///
+/// Modeled on the shell selection used by `watchexec`: callers pick from a closed set of
+/// well-known shells, or name an arbitrary Unix shell binary, instead of juggling a bare
+/// string everywhere a runner needs to be chosen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shell {
+    /// Platform default: `bash` on Linux. On Windows, [`run_script_with_config`] and the
+    /// timeout/elevated paths use PowerShell, while [`spawn_script_with_config`] and
+    /// [`run_script_streaming`] without a timeout use the `run_script` crate's own default, as
+    /// every path does on other platforms.
+    Default,
+    /// A named Unix shell binary, e.g. `"sh"`, `"zsh"`, `"fish"`.
+    Unix(String),
+    /// `cmd.exe`. Windows-only.
+    #[cfg(windows)]
+    Cmd,
+    /// PowerShell: `powershell.exe` on Windows, `pwsh` on Unix where it's installed.
+    Powershell,
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        Self::Default
+    }
+}
+
+impl Shell {
+    /// Binary name passed to `run_script` as the `runner`, or `None` to let it fall back to
+    /// its own platform default.
+    fn runner(&self) -> Option<String> {
+        match self {
+            Self::Default => {
+                #[cfg(target_os = "linux")]
+                {
+                    Some("bash".to_string())
+                }
+                #[cfg(not(target_os = "linux"))]
+                {
+                    None
+                }
+            }
+            Self::Unix(bin) => Some(bin.clone()),
+            #[cfg(windows)]
+            Self::Cmd => Some("cmd".to_string()),
+            Self::Powershell => {
+                #[cfg(windows)]
+                {
+                    Some("powershell".to_string())
+                }
+                #[cfg(not(windows))]
+                {
+                    Some("pwsh".to_string())
+                }
+            }
+        }
+    }
+}
+
+/// Configuration for how a script is executed.
+///
+/// `run_script`/`spawn_script` run with `ScriptConfig::default()`; use
+/// `run_script_with_config`/`spawn_script_with_config` to pick a different shell, pass it
+/// extra arguments, or override the working directory/environment.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptConfig {
+    /// Shell used to run the script.
+    pub shell: Shell,
+    /// Extra arguments inserted before the script itself, e.g. `["-NoLogo", "-Command"]` for
+    /// PowerShell or `["-cu"]` for a POSIX shell.
+    pub shell_args: Option<Vec<String>>,
+    /// Working directory the script is run from. Defaults to the caller's current directory.
+    pub working_directory: Option<String>,
+    /// Extra environment variables exposed to the script.
+    pub env_vars: Option<HashMap<String, String>>,
+    /// Run the script with elevated privileges.
+    ///
+    /// On Unix this prefixes the invocation with [`Self::elevation_command`] (`"sudo"` by
+    /// default). On Windows it relaunches the script through `Start-Process -Verb RunAs`,
+    /// which triggers the UAC prompt; in that case the elevated child runs outside this
+    /// process, so the returned [`ProcessOutput`] may carry only an exit code, with empty
+    /// stdout/stderr.
+    pub elevated: bool,
+    /// Binary used to elevate privileges on Unix when [`Self::elevated`] is set. Defaults to
+    /// `"sudo"`; set to e.g. `"doas"` to use an alternative. Ignored on Windows.
+    pub elevation_command: Option<String>,
+    /// Kill the script and return if it hasn't finished within this long.
+    ///
+    /// When set, `run_script`/`run_script_with_config` spawn the child, wait for it, and if it's
+    /// still running once the timeout elapses, kill it and return a [`ProcessOutput`] with
+    /// `code: -1` and `timed_out: true` instead of waiting forever. On Windows it is not
+    /// applied when [`Self::elevated`] is set; the elevated run is waited on until it exits.
+    pub timeout: Option<std::time::Duration>,
+}
+
+impl ScriptConfig {
+    /// Binary used to elevate privileges on Unix, defaulting to `"sudo"`.
+    #[cfg(unix)]
+    fn elevation_binary(&self) -> &str {
+        self.elevation_command.as_deref().unwrap_or("sudo")
+    }
+}
+
 /// Spawn a script in the foreground, using the appropriate shell
 ///
 /// This must not block. Return the child and the caller may block if they like
 pub fn spawn_script(script: &str) -> anyhow::Result<std::process::Child> {
-    #[cfg(target_os = "linux")]
-    let runner = Some("bash".to_string());
-    #[cfg(not(target_os = "linux"))]
-    let runner = None;
+    spawn_script_with_config(script, &ScriptConfig::default())
+}
+
+/// Like [`spawn_script`], but with a [`ScriptConfig`] to pick the shell, its arguments, the
+/// working directory, and the environment.
+pub fn spawn_script_with_config(
+    script: &str,
+    config: &ScriptConfig,
+) -> anyhow::Result<std::process::Child> {
+    #[cfg(windows)]
+    if config.elevated {
+        return spawn_elevated_windows(script, config);
+    }
 
+    let (runner, runner_args) = runner_and_args(config);
     let options = run_script::ScriptOptions {
         runner,
-        runner_args: None,
-        working_directory: None,
+        runner_args,
+        working_directory: config.working_directory.clone(),
         input_redirection: run_script::types::IoOptions::Inherit,
         output_redirection: run_script::types::IoOptions::Inherit,
         exit_on_error: true,
         print_commands: true,
-        env_vars: None,
+        env_vars: config.env_vars.clone(),
     };
 
     Ok(run_script::spawn_script!(script, &options)?)
 }
 
+/// Spawn `script` under `config` with piped stdout/stderr, via the `run_script` crate.
+fn spawn_piped(script: &str, config: &ScriptConfig) -> anyhow::Result<std::process::Child> {
+    let (runner, runner_args) = runner_and_args(config);
+    let options = run_script::ScriptOptions {
+        runner,
+        runner_args,
+        working_directory: config.working_directory.clone(),
+        input_redirection: run_script::types::IoOptions::Inherit,
+        output_redirection: run_script::types::IoOptions::Pipe,
+        exit_on_error: true,
+        print_commands: false,
+        env_vars: config.env_vars.clone(),
+    };
+    Ok(run_script::spawn_script!(script, &options)?)
+}
+
+/// Spawn `script` under `config` directly (bypassing the `run_script` crate, which gives us
+/// no hook for this), with piped stdout/stderr and, on Unix, in its own session (`setsid`), so
+/// that killing it on a timeout reaches the whole tree rather than just the immediate child.
+/// On Windows no new group is created; [`kill_process_group`] relies on `taskkill /T` there.
+///
+/// On Windows this resolves the shell via [`windows_shell_and_args`] (the same PowerShell
+/// `-File`/`cmd /C` invocation the rest of the crate uses) rather than handing a bare script
+/// path to whatever `default_shell_binary()` returns, since neither `cmd.exe` nor
+/// `powershell.exe` executes a positional path as a script without that flag.
+///
+/// Returns the child along with the temporary script file backing it; the caller is
+/// responsible for removing that file once the child has exited.
+fn spawn_piped_in_new_group(
+    script: &str,
+    config: &ScriptConfig,
+) -> anyhow::Result<(std::process::Child, std::path::PathBuf)> {
+    #[cfg(windows)]
+    let (shell, required_args, ext) = windows_shell_and_args(config);
+    #[cfg(unix)]
+    let (shell, required_args, ext): (String, Vec<String>, Option<&str>) = {
+        let (runner, runner_args) = runner_and_args(config);
+        (
+            runner.unwrap_or_else(default_shell_binary),
+            runner_args.unwrap_or_default(),
+            None,
+        )
+    };
+    let script_path = write_script_to_temp_file(script, ext)?;
+
+    let mut cmd = std::process::Command::new(&shell);
+    cmd.args(&required_args);
+    cmd.arg(&script_path);
+    if let Some(dir) = &config.working_directory {
+        cmd.current_dir(dir);
+    }
+    if let Some(vars) = &config.env_vars {
+        cmd.envs(vars);
+    }
+    cmd.stdin(std::process::Stdio::null());
+    cmd.stdout(std::process::Stdio::piped());
+    cmd.stderr(std::process::Stdio::piped());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Safety: `setsid` is async-signal-safe and the only thing this pre_exec hook does.
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    let child = cmd.spawn()?;
+    Ok((child, script_path))
+}
+
+/// Runner binary used by [`spawn_piped_in_new_group`] (Unix) when [`Shell::runner`] has no
+/// opinion (i.e. [`Shell::Default`] outside Linux).
+fn default_shell_binary() -> String {
+    #[cfg(windows)]
+    {
+        "cmd".to_string()
+    }
+    #[cfg(not(windows))]
+    {
+        "sh".to_string()
+    }
+}
+
+/// Resolve `(shell binary, args to insert before the script path, script file extension)` for
+/// `config.shell` on Windows, shared by [`spawn_piped_in_new_group`] and the elevated-launch
+/// path ([`run_elevated_windows`]/[`spawn_elevated_windows`]).
+///
+/// `cmd.exe` only treats a script path as a command when given `/C`, and `powershell.exe` only
+/// does so when given `-File` and a `.ps1` extension; a bare positional path is silently not
+/// executed by either. [`Shell::Unix`] is passed through unchanged since POSIX shells accept a
+/// script path directly.
+#[cfg(windows)]
+fn windows_shell_and_args(config: &ScriptConfig) -> (String, Vec<String>, Option<&'static str>) {
+    let mut args = config.shell_args.clone().unwrap_or_default();
+    let (shell, ext) = match &config.shell {
+        Shell::Cmd => {
+            args.push("/C".to_string());
+            ("cmd".to_string(), None)
+        }
+        Shell::Unix(bin) => (bin.clone(), None),
+        Shell::Default | Shell::Powershell => {
+            args.push("-NoProfile".to_string());
+            args.push("-NonInteractive".to_string());
+            args.push("-File".to_string());
+            ("powershell.exe".to_string(), Some("ps1"))
+        }
+    };
+    (shell, args, ext)
+}
+
+static TEMP_SCRIPT_SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Write `script` to a uniquely-named file in the temp directory, with `ext` as its extension
+/// (e.g. `Some("ps1")` so `powershell.exe -File` recognizes it), and return its path.
+fn write_script_to_temp_file(
+    script: &str,
+    ext: Option<&str>,
+) -> anyhow::Result<std::path::PathBuf> {
+    let seq = TEMP_SCRIPT_SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let mut name = format!("run-script-rs-{}-{seq}", std::process::id());
+    if let Some(ext) = ext {
+        name.push('.');
+        name.push_str(ext);
+    }
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, script)?;
+    Ok(path)
+}
+
+/// Read `stdout`/`stderr` to completion on separate threads, invoking `on_line` (serialized
+/// onto the calling thread) for each line as it arrives, and returning the accumulated text
+/// of each stream once both have closed.
+fn drain_piped_output(
+    stdout: std::process::ChildStdout,
+    stderr: std::process::ChildStderr,
+    mut on_line: impl FnMut(Line),
+) -> (String, String) {
+    use std::io::{BufRead, BufReader};
+
+    let (tx, rx) = std::sync::mpsc::channel::<Line>();
+    let stdout_tx = tx.clone();
+    let stdout_thread = std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if stdout_tx.send(Line::Stdout(line)).is_err() {
+                break;
+            }
+        }
+    });
+    let stderr_thread = std::thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if tx.send(Line::Stderr(line)).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut stdout_acc = String::new();
+    let mut stderr_acc = String::new();
+    for line in rx {
+        match &line {
+            Line::Stdout(text) => {
+                stdout_acc.push_str(text);
+                stdout_acc.push('\n');
+            }
+            Line::Stderr(text) => {
+                stderr_acc.push_str(text);
+                stderr_acc.push('\n');
+            }
+        }
+        on_line(line);
+    }
+
+    let _ = stdout_thread.join();
+    let _ = stderr_thread.join();
+    (stdout_acc, stderr_acc)
+}
+
+/// Run `drain` (which blocks until the child's stdout/stderr close) concurrently with a
+/// watchdog that kills `pid`'s process group if it's still running once `timeout` elapses.
+/// Returns whether the watchdog fired, followed by `drain`'s result.
+fn drain_with_timeout(
+    pid: u32,
+    timeout: std::time::Duration,
+    drain: impl FnOnce() -> (String, String),
+) -> (bool, String, String) {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Condvar, Mutex};
+
+    let done = Arc::new((Mutex::new(false), Condvar::new()));
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let watchdog = {
+        let done = done.clone();
+        let timed_out = timed_out.clone();
+        std::thread::spawn(move || {
+            let (lock, cvar) = &*done;
+            let guard = lock.lock().unwrap();
+            let (_guard, result) = cvar
+                .wait_timeout_while(guard, timeout, |finished| !*finished)
+                .unwrap();
+            if result.timed_out() {
+                timed_out.store(true, Ordering::SeqCst);
+                kill_process_group(pid);
+            }
+        })
+    };
+
+    let (stdout_acc, stderr_acc) = drain();
+
+    {
+        let (lock, cvar) = &*done;
+        *lock.lock().unwrap() = true;
+        cvar.notify_all();
+    }
+    let _ = watchdog.join();
+
+    (timed_out.load(Ordering::SeqCst), stdout_acc, stderr_acc)
+}
+
+/// Kill every process in `pid`'s process group/session.
+///
+/// On Unix, sends `SIGTERM` then, after a short grace period, `SIGKILL`, to the negative pid
+/// (i.e. the whole group created by [`spawn_piped_in_new_group`]'s `setsid` call). On Windows,
+/// shells out to `taskkill /T /F`, which walks the OS's recorded parent/child tree regardless
+/// of how the child was spawned.
+#[cfg(unix)]
+fn kill_process_group(pid: u32) {
+    // Safety: `pid` is the pid of a process group we created via `setsid` and haven't reaped.
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGTERM);
+    }
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill_process_group(pid: u32) {
+    let _ = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .output();
+}
+
+/// Spawn `script` under `config` and wait for it, killing its whole process tree if it runs
+/// past `timeout`.
+///
+/// Unlike [`spawn_script_with_config`], this pipes stdout/stderr so a run that finishes in
+/// time still gets its captured output, not just an exit code.
+fn run_script_with_timeout(
+    script: &str,
+    config: &ScriptConfig,
+    timeout: std::time::Duration,
+) -> anyhow::Result<ProcessOutput> {
+    let (mut child, script_path) = spawn_piped_in_new_group(script, config)?;
+    let stdout = child
+        .stdout
+        .take()
+        .expect("child spawned with piped stdout");
+    let stderr = child
+        .stderr
+        .take()
+        .expect("child spawned with piped stderr");
+    let pid = child.id();
+
+    let (timed_out, stdout_acc, stderr_acc) =
+        drain_with_timeout(pid, timeout, || drain_piped_output(stdout, stderr, |_| {}));
+
+    let status = child.wait()?;
+    let _ = std::fs::remove_file(&script_path);
+
+    Ok(ProcessOutput {
+        code: if timed_out {
+            -1
+        } else {
+            status.code().unwrap_or(-1)
+        },
+        stdout: stdout_acc.trim_end().to_string(),
+        stderr: stderr_acc.trim_end().to_string(),
+        timed_out,
+    })
+}
+
+/// Resolve the effective `(runner, runner_args)` pair for `config`, prefixing with the Unix
+/// elevation binary (`sudo`/`doas`/...) when [`ScriptConfig::elevated`] is set.
+fn runner_and_args(config: &ScriptConfig) -> (Option<String>, Option<Vec<String>>) {
+    let runner = config.shell.runner();
+    let runner_args = config.shell_args.clone();
+
+    #[cfg(unix)]
+    if config.elevated {
+        let mut args = vec![runner.unwrap_or_else(|| "sh".to_string())];
+        args.extend(runner_args.unwrap_or_default());
+        return (Some(config.elevation_binary().to_string()), Some(args));
+    }
+
+    (runner, runner_args)
+}
+
 /// Run a script.
 ///
 /// On windows, it uses powershell. On Unix, default shell.
 ///
-/// # Important
+/// # Note
 ///
-/// - Powershell script must be a single line. Use `;` instead of `\n` to
-///   separate lines.
+/// Multi-line powershell scripts are run via `powershell.exe -EncodedCommand`, so `\n` works
+/// as a line separator; it no longer has to be a single line with `;`.
 pub fn run_script(script: &str, verbose: bool) -> anyhow::Result<ProcessOutput> {
+    run_script_with_config(script, &ScriptConfig::default(), verbose)
+}
+
+/// Like [`run_script`], but with a [`ScriptConfig`] to pick the shell, its arguments, the
+/// working directory, and the environment.
+pub fn run_script_with_config(
+    script: &str,
+    config: &ScriptConfig,
+    verbose: bool,
+) -> anyhow::Result<ProcessOutput> {
+    #[cfg(windows)]
+    if config.elevated {
+        let s = run_elevated_windows(script, config, verbose)?;
+        if verbose {
+            println!(" {s:?}");
+        }
+        return Ok(s);
+    }
+
+    if let Some(timeout) = config.timeout {
+        return run_script_with_timeout(script, config, timeout);
+    }
+
     #[cfg(unix)]
     {
-        let options = run_script::ScriptOptions::new();
+        let (runner, runner_args) = runner_and_args(config);
+        let mut options = run_script::ScriptOptions::new();
+        options.runner = runner;
+        options.runner_args = runner_args;
+        options.working_directory = config.working_directory.clone();
+        options.env_vars = config.env_vars.clone();
         if verbose {
             println!("Executing `{script}` using {options:?}.");
         }
@@ -45,6 +492,7 @@ pub fn run_script(script: &str, verbose: bool) -> anyhow::Result<ProcessOutput>
                 code: status,
                 stderr: err.trim_end().to_string(),
                 stdout: out.trim_end().to_string(),
+                timed_out: false,
             })?;
         if verbose {
             println!(" {s:?}");
@@ -54,17 +502,211 @@ pub fn run_script(script: &str, verbose: bool) -> anyhow::Result<ProcessOutput>
 
     #[cfg(windows)]
     {
-        let s = run_powershell(script, verbose)?;
-        if verbose {
-            println!(" {s:?}");
+        match config.shell {
+            Shell::Default | Shell::Powershell => {
+                let s = run_powershell(script, config, verbose)?;
+                if verbose {
+                    println!(" {s:?}");
+                }
+                Ok(s)
+            }
+            _ => {
+                let (runner, runner_args) = runner_and_args(config);
+                let mut options = run_script::ScriptOptions::new();
+                options.runner = runner;
+                options.runner_args = runner_args;
+                options.working_directory = config.working_directory.clone();
+                options.env_vars = config.env_vars.clone();
+                if verbose {
+                    println!("Executing `{script}` using {options:?}.");
+                }
+                let s = run_script::run(script, &vec![], &options).map(|(status, out, err)| {
+                    ProcessOutput {
+                        code: status,
+                        stderr: err.trim_end().to_string(),
+                        stdout: out.trim_end().to_string(),
+                        timed_out: false,
+                    }
+                })?;
+                if verbose {
+                    println!(" {s:?}");
+                }
+                Ok(s)
+            }
         }
-        Ok(s)
     }
 }
 
+/// A line of output produced by a script run with [`run_script_streaming`], tagged by which
+/// stream it came from.
+#[derive(Debug, Clone)]
+pub enum Line {
+    /// A line read from the child's stdout.
+    Stdout(String),
+    /// A line read from the child's stderr.
+    Stderr(String),
+}
+
+/// Like [`run_script`], but invoke `on_line` for each line of stdout/stderr as it arrives
+/// instead of buffering the whole output until the script finishes.
+///
+/// Still returns a final [`ProcessOutput`] with the exit code and the full accumulated
+/// stdout/stderr, in case the caller wants both the live feed and the complete text.
+///
+/// Honors [`ScriptConfig::timeout`] the same way [`run_script_with_config`] does, killing the
+/// whole process group and returning `code: -1`/`timed_out: true` if it fires. [`ScriptConfig::elevated`]
+/// is not supported here: an elevated child runs outside this process, so there is nothing to
+/// pipe or stream, and this returns an error instead of silently falling back to unelevated
+/// execution.
+pub fn run_script_streaming(
+    script: &str,
+    config: &ScriptConfig,
+    on_line: impl FnMut(Line),
+) -> anyhow::Result<ProcessOutput> {
+    if config.elevated {
+        anyhow::bail!(
+            "run_script_streaming does not support ScriptConfig::elevated: an elevated child's \
+             output cannot be captured or streamed"
+        );
+    }
+
+    if let Some(timeout) = config.timeout {
+        let (mut child, script_path) = spawn_piped_in_new_group(script, config)?;
+        let stdout = child
+            .stdout
+            .take()
+            .expect("child spawned with piped stdout");
+        let stderr = child
+            .stderr
+            .take()
+            .expect("child spawned with piped stderr");
+        let pid = child.id();
+
+        let (timed_out, stdout_acc, stderr_acc) = drain_with_timeout(pid, timeout, move || {
+            drain_piped_output(stdout, stderr, on_line)
+        });
+
+        let status = child.wait()?;
+        let _ = std::fs::remove_file(&script_path);
+
+        return Ok(ProcessOutput {
+            code: if timed_out {
+                -1
+            } else {
+                status.code().unwrap_or(-1)
+            },
+            stdout: stdout_acc.trim_end().to_string(),
+            stderr: stderr_acc.trim_end().to_string(),
+            timed_out,
+        });
+    }
+
+    let mut child = spawn_piped(script, config)?;
+    let stdout = child
+        .stdout
+        .take()
+        .expect("child spawned with piped stdout");
+    let stderr = child
+        .stderr
+        .take()
+        .expect("child spawned with piped stderr");
+
+    let (stdout_acc, stderr_acc) = drain_piped_output(stdout, stderr, on_line);
+    let status = child.wait()?;
+
+    Ok(ProcessOutput {
+        code: status.code().unwrap_or(-1),
+        stdout: stdout_acc.trim_end().to_string(),
+        stderr: stderr_acc.trim_end().to_string(),
+        timed_out: false,
+    })
+}
+
+/// Build a `Start-Process -Verb RunAs` command line that relaunches `shell_exe` (plus
+/// `extra_args` and `script_path`) elevated, triggering the UAC prompt.
+#[cfg(windows)]
+fn runas_launcher(shell_exe: &str, extra_args: &[String], script_path: &std::path::Path) -> String {
+    let mut args: Vec<String> = extra_args.to_vec();
+    args.push(script_path.display().to_string());
+    let arg_list = args
+        .iter()
+        .map(|a| format!("'{}'", a.replace('\'', "''")))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("Start-Process -FilePath '{shell_exe}' -ArgumentList {arg_list} -Verb RunAs -Wait")
+}
+
+/// Run `script` elevated via `Start-Process -Verb RunAs`.
+///
+/// The elevated process runs in a separate session, so its stdout/stderr cannot be captured
+/// here; the returned [`ProcessOutput`] carries only the exit code of the launcher.
+#[cfg(windows)]
+fn run_elevated_windows(
+    script: &str,
+    config: &ScriptConfig,
+    debug: bool,
+) -> anyhow::Result<ProcessOutput> {
+    let (shell_exe, extra_args, ext) = windows_shell_and_args(config);
+    let script_path = write_script_to_temp_file(script, ext)?;
+    let launcher = runas_launcher(&shell_exe, &extra_args, &script_path);
+
+    if debug {
+        println!("Executing elevated via `{launcher}`.");
+    }
+
+    let status = std::process::Command::new("powershell.exe")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &launcher])
+        .status()?;
+
+    let _ = std::fs::remove_file(&script_path);
+
+    Ok(ProcessOutput::new(
+        status.code().unwrap_or(-1),
+        String::new(),
+        String::new(),
+    ))
+}
+
+/// Spawn `script` elevated via `Start-Process -Verb RunAs`, without waiting for it.
+///
+/// The returned [`std::process::Child`] is the launcher process, not the elevated script
+/// itself, since Windows gives no handle to a process relaunched across the UAC boundary.
+#[cfg(windows)]
+fn spawn_elevated_windows(
+    script: &str,
+    config: &ScriptConfig,
+) -> anyhow::Result<std::process::Child> {
+    let (shell_exe, extra_args, ext) = windows_shell_and_args(config);
+    let script_path = write_script_to_temp_file(script, ext)?;
+    let launcher = runas_launcher(&shell_exe, &extra_args, &script_path);
+
+    Ok(std::process::Command::new("powershell.exe")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &launcher])
+        .spawn()?)
+}
+
 /// Execute a powershell script in silent mode.
+///
+/// Multi-line scripts are auto-detected (any `\n`) and run via
+/// `powershell.exe -EncodedCommand` instead, since `powershell_script` otherwise requires a
+/// single line with `;` separators. `PsScriptBuilder` has no hook for extra shell arguments, a
+/// working directory or environment variables, so whenever `config` asks for any of these, the
+/// encoded path is used instead, since it invokes `powershell.exe` through a plain
+/// [`std::process::Command`] we control directly.
 #[cfg(windows)]
-fn run_powershell(command: &str, debug: bool) -> anyhow::Result<ProcessOutput> {
+fn run_powershell(
+    command: &str,
+    config: &ScriptConfig,
+    debug: bool,
+) -> anyhow::Result<ProcessOutput> {
+    if command.contains('\n')
+        || config.shell_args.is_some()
+        || config.working_directory.is_some()
+        || config.env_vars.is_some()
+    {
+        return run_powershell_encoded(command, config, debug);
+    }
+
     let ps = powershell_script::PsScriptBuilder::new()
         .hidden(true)
         .no_profile(true)
@@ -87,17 +729,73 @@ fn run_powershell(command: &str, debug: bool) -> anyhow::Result<ProcessOutput> {
     ))
 }
 
+/// Run a (possibly multi-line) powershell script via `-EncodedCommand`.
+///
+/// The script is encoded as UTF-16LE and then Base64, which is how tooling passes complex
+/// multi-line scripts to PowerShell without quoting/escaping headaches. `config.shell_args` are
+/// passed ahead of the `-EncodedCommand` invocation, and `config.working_directory` and
+/// `config.env_vars` are applied directly on the underlying [`std::process::Command`].
+#[cfg(windows)]
+fn run_powershell_encoded(
+    script: &str,
+    config: &ScriptConfig,
+    debug: bool,
+) -> anyhow::Result<ProcessOutput> {
+    use base64::Engine;
+
+    let utf16le: Vec<u8> = script
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect();
+    let encoded = base64::engine::general_purpose::STANDARD.encode(utf16le);
+
+    if debug {
+        println!("Executing encoded multi-line script via `powershell.exe -EncodedCommand`.");
+    }
+
+    let mut cmd = std::process::Command::new("powershell.exe");
+    if let Some(args) = &config.shell_args {
+        cmd.args(args);
+    }
+    cmd.args(["-NoProfile", "-NonInteractive", "-EncodedCommand", &encoded]);
+    if let Some(dir) = &config.working_directory {
+        cmd.current_dir(dir);
+    }
+    if let Some(vars) = &config.env_vars {
+        cmd.envs(vars);
+    }
+    let output = cmd.output()?;
+
+    let s = ProcessOutput::new(
+        output.status.code().unwrap_or(1),
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string(),
+        String::from_utf8_lossy(&output.stderr)
+            .trim_end()
+            .to_string(),
+    );
+    if debug {
+        println!(" {s:?}");
+    }
+    Ok(s)
+}
+
 /// Execution status of an Process/Child.
 ///
 /// It is a triple (`i32`, `String`, `String`).
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct ProcessOutput {
-    /// return code.
+    /// return code. Set to `-1` when [`Self::timed_out`] is `true`.
     pub code: i32,
     /// Captured stdout.
     pub stdout: String,
     /// Captured stderr.
     pub stderr: String,
+    /// Set when [`ScriptConfig::timeout`] expired before the script finished, in which case
+    /// the process was killed and `code` carries the sentinel value `-1` rather than a real
+    /// exit code.
+    pub timed_out: bool,
 }
 
 impl ProcessOutput {
@@ -107,6 +805,7 @@ impl ProcessOutput {
             code,
             stdout,
             stderr,
+            timed_out: false,
         }
     }
 
@@ -130,6 +829,149 @@ impl std::fmt::Display for ProcessOutput {
     }
 }
 
+/// Register `script` to run automatically when the user logs in, under `name`.
+///
+/// This is unprivileged and doesn't involve a service manager: on Windows it writes `script`
+/// to a file under `%APPDATA%\run-script-rs\startup` and points `name` under
+/// `HKEY_CURRENT_USER\Software\Microsoft\Windows\CurrentVersion\Run` at
+/// `powershell.exe -File <that file>`; on Unix it writes `script` to
+/// `~/.config/autostart/<name>.sh` and an XDG autostart entry at
+/// `~/.config/autostart/<name>.desktop` whose `Exec=` runs that file. Routing through a file
+/// (rather than splicing `script` into the `Run` value or `Exec=` line directly) means a
+/// multi-line script doesn't corrupt either format. Calling this again with the same `name`
+/// overwrites the previous entry.
+pub fn register_startup(name: &str, script: &str) -> anyhow::Result<()> {
+    validate_startup_name(name)?;
+
+    #[cfg(windows)]
+    {
+        let script_path = write_startup_script_windows(name, script)?;
+        let command = format!(
+            "powershell.exe -NoProfile -NonInteractive -File \"{}\"",
+            script_path.display()
+        );
+        let hkcu = winreg::RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+        let (run_key, _) = hkcu.create_subkey(WINDOWS_RUN_KEY)?;
+        run_key.set_value(name, &command)?;
+    }
+
+    #[cfg(unix)]
+    {
+        let dir = autostart_dir()?;
+        std::fs::create_dir_all(&dir)?;
+
+        let script_path = dir.join(format!("{name}.sh"));
+        std::fs::write(&script_path, script)?;
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = std::fs::metadata(&script_path)?.permissions();
+            perms.set_mode(0o700);
+            std::fs::set_permissions(&script_path, perms)?;
+        }
+
+        // Run via the same shell `Shell::Default` resolves to elsewhere in the crate (`bash` on
+        // Linux), not a hardcoded `sh`, so autostarted scripts see the same shell a caller would
+        // get from `run_script()`.
+        let shell = Shell::Default.runner().unwrap_or_else(default_shell_binary);
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName={name}\nExec={shell} \"{}\"\nX-GNOME-Autostart-enabled=true\n",
+            script_path.display()
+        );
+        std::fs::write(dir.join(format!("{name}.desktop")), entry)?;
+    }
+
+    Ok(())
+}
+
+/// Undo a previous [`register_startup`] call for `name`. Not an error if `name` isn't
+/// registered.
+pub fn unregister_startup(name: &str) -> anyhow::Result<()> {
+    validate_startup_name(name)?;
+
+    #[cfg(windows)]
+    {
+        let hkcu = winreg::RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+        match hkcu.open_subkey_with_flags(WINDOWS_RUN_KEY, winreg::enums::KEY_SET_VALUE) {
+            Ok(run_key) => match run_key.delete_value(name) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        let script_path = windows_startup_script_path(name)?;
+        if script_path.exists() {
+            std::fs::remove_file(script_path)?;
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        let dir = autostart_dir()?;
+        for ext in ["desktop", "sh"] {
+            let path = dir.join(format!("{name}.{ext}"));
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reject `name`s that aren't safe to use as a bare filename / registry value name, e.g.
+/// `".."` or anything containing a path separator, which would otherwise let a caller escape
+/// [`autostart_dir`] (Unix) or collide with an unrelated `Run` value (Windows). Also rejects
+/// control characters (e.g. `\n`), which would otherwise let `name` inject extra lines into the
+/// generated `.desktop` file (such as a second `Exec=`) beyond the one `register_startup` wrote.
+fn validate_startup_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.contains(['/', '\\'])
+        || name.chars().any(|c| c.is_control())
+    {
+        anyhow::bail!("invalid startup entry name: {name:?}");
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+const WINDOWS_RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+
+/// Where `register_startup`'s script file lives on Windows for a given `name`.
+#[cfg(windows)]
+fn windows_startup_script_path(name: &str) -> anyhow::Result<std::path::PathBuf> {
+    let appdata = std::env::var("APPDATA").map_err(|_| anyhow::anyhow!("APPDATA is not set"))?;
+    Ok(std::path::PathBuf::from(appdata)
+        .join("run-script-rs")
+        .join("startup")
+        .join(format!("{name}.ps1")))
+}
+
+/// Write `script` to [`windows_startup_script_path`] for `name`, creating parent directories
+/// as needed, and return the path.
+#[cfg(windows)]
+fn write_startup_script_windows(name: &str, script: &str) -> anyhow::Result<std::path::PathBuf> {
+    let path = windows_startup_script_path(name)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, script)?;
+    Ok(path)
+}
+
+/// `~/.config/autostart`, where XDG autostart `.desktop` entries live.
+#[cfg(unix)]
+fn autostart_dir() -> anyhow::Result<std::path::PathBuf> {
+    let home = std::env::var("HOME").map_err(|_| anyhow::anyhow!("HOME is not set"))?;
+    Ok(std::path::PathBuf::from(home)
+        .join(".config")
+        .join("autostart"))
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
@@ -169,12 +1011,13 @@ ls $TEMP
     #[test]
     #[cfg(windows)]
     fn test_powershell() {
-        let out = run_powershell("ls", true).unwrap();
+        let out = run_powershell("ls", &ScriptConfig::default(), true).unwrap();
         assert!(!out.stdout.is_empty());
         println!("output=`{out:?}`");
 
         let uuid = run_powershell(
             r"(Get-ItemProperty -Path Registry::HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Cryptography).MachineGuid",
+            &ScriptConfig::default(),
             true,
         ).unwrap().stdout;
         assert!(!uuid.is_empty());
@@ -208,6 +1051,96 @@ ls $TEMP
         assert!(x.stdout.len() > 1);
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_script_timeout_kills_long_running_script() {
+        let config = ScriptConfig {
+            timeout: Some(std::time::Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let start = std::time::Instant::now();
+        let x = run_script_with_config("sleep 5", &config, true).unwrap();
+        assert!(x.timed_out);
+        assert_eq!(x.code, -1);
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_script_timeout_still_captures_output_when_not_exceeded() {
+        let config = ScriptConfig {
+            timeout: Some(std::time::Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let x = run_script_with_config("echo hello", &config, true).unwrap();
+        assert!(!x.timed_out);
+        assert_eq!(x.code, 0);
+        assert_eq!(x.stdout, "hello");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_script_streaming_calls_on_line_for_each_line() {
+        let lines = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let lines_cb = lines.clone();
+        let x = run_script_streaming(
+            "echo one; echo two >&2; echo three",
+            &ScriptConfig::default(),
+            move |line| lines_cb.lock().unwrap().push(line),
+        )
+        .unwrap();
+
+        assert_eq!(x.code, 0);
+        assert!(!x.timed_out);
+        assert_eq!(x.stdout, "one\nthree");
+        assert_eq!(x.stderr, "two");
+
+        // Stdout and stderr are drained by separate threads, so their relative interleaving in
+        // `lines` isn't guaranteed; only the order within each stream is.
+        let lines = lines.lock().unwrap();
+        assert_eq!(lines.len(), 3);
+        let stdout_lines: Vec<&str> = lines
+            .iter()
+            .filter_map(|l| match l {
+                Line::Stdout(s) => Some(s.as_str()),
+                Line::Stderr(_) => None,
+            })
+            .collect();
+        assert_eq!(stdout_lines, vec!["one", "three"]);
+        let stderr_lines: Vec<&str> = lines
+            .iter()
+            .filter_map(|l| match l {
+                Line::Stderr(s) => Some(s.as_str()),
+                Line::Stdout(_) => None,
+            })
+            .collect();
+        assert_eq!(stderr_lines, vec!["two"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_script_streaming_honors_timeout() {
+        let config = ScriptConfig {
+            timeout: Some(std::time::Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let start = std::time::Instant::now();
+        let x = run_script_streaming("sleep 5", &config, |_| {}).unwrap();
+        assert!(x.timed_out);
+        assert_eq!(x.code, -1);
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_script_streaming_rejects_elevated() {
+        let config = ScriptConfig {
+            elevated: true,
+            ..Default::default()
+        };
+        assert!(run_script_streaming("echo hi", &config, |_| {}).is_err());
+    }
+
     #[test]
     #[cfg(windows)]
     fn test_script() {